@@ -0,0 +1,54 @@
+use crate::Tags;
+
+/// Bicycle access for a way, as a single overall verdict (see [`resolve_access`] for why this
+/// isn't resolved per-direction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Allowed,
+    Dismount,
+    Forbidden,
+}
+
+/// Resolves whether cycling is allowed, following the OSM access hierarchy: a general `access`
+/// tag sets a baseline, then vehicle-class tags (`vehicle`, then the more specific `bicycle`)
+/// override it. A bare `access=private` is not itself a cycling restriction -- only `access=no`
+/// is -- since many jurisdictions treat private land as still cycle-legal without an explicit
+/// `bicycle=private`.
+///
+/// `bicycle:forward` / `bicycle:backward` and `*:conditional` tags (`access:conditional`,
+/// `vehicle:conditional`, `bicycle:conditional`) are deliberately NOT modeled here: this crate
+/// has no per-direction routing graph and no notion of time, so parsing them and then ignoring
+/// the result would be misleading. Route around them upstream if you need them; this function
+/// only returns the base, direction- and time-independent verdict.
+pub fn resolve_access(tags: &Tags, msgs: &mut Vec<String>) -> Access {
+    let mut access = general_access(tags.get("access"));
+
+    if let Some(vehicle) = tags.get("vehicle") {
+        access = general_access(Some(vehicle));
+    }
+
+    if let Some(bicycle) = tags.get("bicycle") {
+        if let Some(resolved) = bicycle_access(bicycle) {
+            msgs.push(format!("bicycle={bicycle} overrides general access"));
+            access = resolved;
+        }
+    }
+
+    access
+}
+
+fn general_access(value: Option<&str>) -> Access {
+    match value {
+        Some("no") => Access::Forbidden,
+        _ => Access::Allowed,
+    }
+}
+
+fn bicycle_access(value: &str) -> Option<Access> {
+    match value {
+        "yes" | "permissive" | "designated" => Some(Access::Allowed),
+        "dismount" => Some(Access::Dismount),
+        "no" | "use_sidepath" | "private" => Some(Access::Forbidden),
+        _ => None,
+    }
+}
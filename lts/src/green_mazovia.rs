@@ -1,11 +1,28 @@
-use crate::{parse, Tags, LTS};
+use crate::access::{resolve_access, Access};
+use crate::speed::{resolve_speed_and_surface, Surface};
+use crate::{Tags, LTS};
+
+/// The outcome of [`evaluate_cycling_permission`]: whether a way can be ridden at all, and if
+/// so, whether it requires dismounting.
+pub(crate) enum Permission {
+    Allowed,
+    Dismount,
+    Forbidden,
+}
 
 // A flow chart would explain this nicely
 pub fn green_mazovia(tags: &Tags) -> (LTS, Vec<String>) {
     let mut msgs = Vec::new();
 
-    if is_cycling_forbidden(&tags, &mut msgs) {
-        return (LTS::NotAllowed, msgs);
+    match evaluate_cycling_permission(&tags, &mut msgs) {
+        Permission::Forbidden => return (LTS::NotAllowed, msgs),
+        Permission::Dismount => {
+            // Dismounting is stressful and slow, but it's still cyclable -- unlike a genuinely
+            // forbidden way, it shouldn't be excluded from the network entirely.
+            msgs.push("Dismount-only way; rating LTS4 instead of excluding it".into());
+            return (LTS::LTS4, msgs);
+        }
+        Permission::Allowed => {}
     }
 
     if let Some(lts) = separate_path(&tags, &mut msgs) {
@@ -29,7 +46,7 @@ pub fn green_mazovia(tags: &Tags) -> (LTS, Vec<String>) {
     (LTS::NotAllowed, msgs)
 }
 
-fn non_bicycle_infrastructure(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
+pub(crate) fn non_bicycle_infrastructure(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
     if tags.is("highway", "path") {
         msgs.push(format!(
             "This way is a separated path because highway={}, but not suitable for all bicycles",
@@ -41,7 +58,7 @@ fn non_bicycle_infrastructure(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS
     None
 }
 
-fn separate_path(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
+pub(crate) fn separate_path(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
     if tags.is("highway", "cycleway")
         && tags.is("crossing", "traffic_signals") {
         msgs.push(format!(
@@ -84,7 +101,7 @@ fn separate_path(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
     None
 }
 
-fn is_bike_lane(tags: &Tags, msgs: &mut Vec<String>) -> bool {
+pub(crate) fn is_bike_lane(tags: &Tags, msgs: &mut Vec<String>) -> bool {
     let mut has_lane = false;
     if let Some((key, value)) = tags.prefix_is_any(
         "cycleway",
@@ -110,10 +127,10 @@ fn is_bike_lane(tags: &Tags, msgs: &mut Vec<String>) -> bool {
     return has_lane;
 }
 
-fn is_mixed_traffic(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
+pub(crate) fn is_mixed_traffic(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
     msgs.push("No bike lane or separated path; treating as mixed traffic".into());
 
-    let speed_limit = parse::get_maxspeed_kmph(tags, msgs);
+    let resolved = resolve_speed_and_surface(tags, msgs);
 
     if tags.is("motor_vehicle", "no") || tags.is("motorcar", "no") {
         msgs.push("Motor vehicles not allowed, so LTS 1".into());
@@ -131,35 +148,60 @@ fn is_mixed_traffic(tags: &Tags, msgs: &mut Vec<String>) -> Option<LTS> {
         return Some(LTS::LTS2);
     }
 
-    if speed_limit <= 30 {
-        msgs.push("LTS 2 because speed is below 30 kmph".into());
-        return Some(LTS::LTS2);
+    let mut lts = if resolved.speed_kmph <= 30.0 {
+        msgs.push(format!(
+            "LTS 2 because resolved speed {} kmph is at or below 30",
+            resolved.speed_kmph
+        ));
+        LTS::LTS2
+    } else if resolved.speed_kmph <= 50.0 {
+        msgs.push(format!(
+            "LTS 3 because resolved speed {} kmph is at or below 50",
+            resolved.speed_kmph
+        ));
+        LTS::LTS3
+    } else {
+        msgs.push(format!(
+            "LTS 4 because resolved speed {} kmph is over 50",
+            resolved.speed_kmph
+        ));
+        LTS::LTS4
+    };
+
+    if resolved.surface == Surface::Unpaved {
+        lts = match lts {
+            LTS::LTS1 | LTS::LTS2 => {
+                msgs.push("Bumping up to LTS 3 because the surface is unpaved".into());
+                LTS::LTS3
+            }
+            other => other,
+        };
     }
 
-    msgs.push("LTS 4 because speed is over 30 kmph".into());
-    Some(LTS::LTS4)
+    Some(lts)
 }
 
-fn is_cycling_forbidden(tags: &Tags, msgs: &mut Vec<String>) -> bool {
+pub(crate) fn evaluate_cycling_permission(tags: &Tags, msgs: &mut Vec<String>) -> Permission {
     if !tags.has("highway") && !tags.has("bicycle") {
         msgs.push("Way doesn't have a highway or bicycle tag".into());
-        return true;
+        return Permission::Forbidden;
     }
 
     if tags.is("motorroad", "yes") {
         msgs.push("Bicycles are not allowed on motorroads".into());
-        return true;
-    }
-
-    if tags.is_any("bicycle", vec!["no", "use_sidepath"]) {
-        msgs.push("Cycling not permitted due to bicycle=no".into());
-        return true;
+        return Permission::Forbidden;
     }
 
-    if tags.is("access", "no") {
-        // TODO There are exceptions for bicycle
-        msgs.push("Cycling not permitted due to access=no".into());
-        return true;
+    match resolve_access(tags, msgs) {
+        Access::Forbidden => {
+            msgs.push("Cycling not permitted by access tags".into());
+            return Permission::Forbidden;
+        }
+        Access::Dismount => {
+            msgs.push("Access tags require dismounting".into());
+            return Permission::Dismount;
+        }
+        Access::Allowed => {}
     }
 
     if tags.is_any(
@@ -170,14 +212,14 @@ fn is_cycling_forbidden(tags: &Tags, msgs: &mut Vec<String>) -> bool {
             "Cycling not permitted due to highway={}",
             tags.get("highway").unwrap()
         ));
-        return true;
+        return Permission::Forbidden;
     }
 
     if let Some((key, value)) = tags.prefix_is_any("cycleway", vec!["separate"]) {
         msgs.push(format!(
             "Cycling not permitted because there is separate cycleway {key}={value}"
         ));
-        return true;
+        return Permission::Forbidden;
     }
 
     if tags.is_any("highway", vec!["footway"])
@@ -187,8 +229,8 @@ fn is_cycling_forbidden(tags: &Tags, msgs: &mut Vec<String>) -> bool {
             "Cycling not permitted on highway={}, when footway and bicycle=yes|separated|designated is missing",
             tags.get("highway").unwrap()
         ));
-        return true;
+        return Permission::Forbidden;
     }
 
-    false
+    Permission::Allowed
 }
@@ -0,0 +1,28 @@
+use crate::{green_mazovia, Tags, LTS};
+
+/// A pluggable LTS classifier. Different regions apply different thresholds and tag
+/// interpretations (US BikeOttawa-style, UK, etc), so this lets callers pick one at runtime
+/// instead of hardcoding `green_mazovia`.
+pub trait LtsProfile {
+    /// Classifies a way's tags into an LTS level, along with a human-readable explanation of the
+    /// decision, one entry per rule considered.
+    fn classify(&self, tags: &Tags) -> (LTS, Vec<String>);
+}
+
+/// The LTS profile devised by Zielona Mazowsze, a Warsaw-area cycling advocacy group.
+pub struct GreenMazovia;
+
+impl LtsProfile for GreenMazovia {
+    fn classify(&self, tags: &Tags) -> (LTS, Vec<String>) {
+        green_mazovia::green_mazovia(tags)
+    }
+}
+
+/// Resolves a profile name (as given to `--lts-profile`) to its implementation. Returns `None`
+/// for an unrecognized name.
+pub fn resolve_profile(name: &str) -> Option<Box<dyn LtsProfile>> {
+    match name {
+        "green-mazovia" => Some(Box::new(GreenMazovia)),
+        _ => None,
+    }
+}
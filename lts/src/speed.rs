@@ -0,0 +1,120 @@
+use crate::{parse, Tags};
+
+/// The speed and surface cyclists will actually experience on a way, resolved from tags and
+/// fallback defaults. Feeds both LTS classification and, eventually, routing cost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedAndSurface {
+    pub speed_kmph: f64,
+    pub surface: Surface,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    Paved,
+    Unpaved,
+}
+
+/// Resolves the speed a way carries, falling back through `maxspeed` -> an implicit zone default
+/// from `maxspeed:type`/`zone:maxspeed` -> a per-`highway` class default, mirroring how SUMO's
+/// OSM importer derives edge speeds when `maxspeed` is absent. Lane count and `surface`/
+/// `smoothness` are folded in too, since a 3-lane road or an unpaved track isn't comparable to a
+/// 2-lane paved one even at the same posted limit.
+pub fn resolve_speed_and_surface(tags: &Tags, msgs: &mut Vec<String>) -> SpeedAndSurface {
+    let mut speed_kmph = parse::get_maxspeed_kmph(tags, msgs);
+
+    if tags.get("maxspeed").is_none() {
+        if let Some(zone_speed) = implicit_zone_speed(tags) {
+            msgs.push(format!(
+                "No maxspeed; using implicit zone speed {zone_speed} kmph"
+            ));
+            speed_kmph = zone_speed;
+        } else if let Some(class_speed) = highway_class_default(tags) {
+            msgs.push(format!(
+                "No maxspeed or zone; using highway class default {class_speed} kmph"
+            ));
+            speed_kmph = class_speed;
+        }
+    }
+
+    if let Some(lanes) = tags.get("lanes").and_then(|l| l.parse::<u32>().ok()) {
+        if lanes >= 3 {
+            msgs.push(format!("{lanes} lanes bumps effective speed up by 10 kmph"));
+            speed_kmph += 10.0;
+        }
+    }
+
+    let surface = resolve_surface(tags, msgs);
+
+    SpeedAndSurface {
+        speed_kmph,
+        surface,
+    }
+}
+
+fn implicit_zone_speed(tags: &Tags) -> Option<f64> {
+    match tags
+        .get("maxspeed:type")
+        .or_else(|| tags.get("zone:maxspeed"))
+    {
+        Some("DE:zone30") | Some("DE:zone:30") => Some(30.0),
+        Some("DE:urban") | Some("PL:urban") => Some(50.0),
+        Some("DE:rural") | Some("PL:rural") => Some(90.0),
+        Some("DE:motorway") => Some(130.0),
+        _ => None,
+    }
+}
+
+fn highway_class_default(tags: &Tags) -> Option<f64> {
+    match tags.get("highway") {
+        Some("motorway") | Some("motorway_link") => Some(120.0),
+        Some("trunk") | Some("trunk_link") => Some(100.0),
+        Some("primary") | Some("primary_link") => Some(80.0),
+        Some("secondary") | Some("secondary_link") => Some(70.0),
+        Some("tertiary") | Some("tertiary_link") => Some(60.0),
+        Some("unclassified") | Some("residential") => Some(50.0),
+        Some("living_street") => Some(15.0),
+        Some("service") | Some("track") => Some(20.0),
+        _ => None,
+    }
+}
+
+fn resolve_surface(tags: &Tags, msgs: &mut Vec<String>) -> Surface {
+    if let Some(surface) = tags.get("surface") {
+        if is_unpaved_surface(surface) {
+            msgs.push(format!("surface={surface} is unpaved"));
+            return Surface::Unpaved;
+        }
+    }
+
+    if let Some(smoothness) = tags.get("smoothness") {
+        if is_bad_smoothness(smoothness) {
+            msgs.push(format!("smoothness={smoothness} is bad"));
+            return Surface::Unpaved;
+        }
+    }
+
+    Surface::Paved
+}
+
+fn is_unpaved_surface(surface: &str) -> bool {
+    matches!(
+        surface,
+        "unpaved"
+            | "gravel"
+            | "dirt"
+            | "ground"
+            | "grass"
+            | "sand"
+            | "mud"
+            | "earth"
+            | "compacted"
+            | "fine_gravel"
+    )
+}
+
+fn is_bad_smoothness(smoothness: &str) -> bool {
+    matches!(
+        smoothness,
+        "bad" | "very_bad" | "horrible" | "very_horrible" | "impassable"
+    )
+}
@@ -0,0 +1,85 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use geojson::{GeoJson, Value};
+use indicatif::HumanCount;
+
+use crate::osm2network::Network;
+use crate::router::{Profile, Router};
+
+/// Computes everything reachable from the origin points in `origins_path` within `limit` cost
+/// units, and writes cost-per-edge to `output_path`.
+pub fn run(
+    network: &Network,
+    origins_path: &str,
+    limit: usize,
+    profile: Profile,
+    blend: f64,
+    output_path: &str,
+) -> Result<()> {
+    let mut start = Instant::now();
+    println!("Loading origins from {origins_path}");
+    let origins = load_origins(origins_path)?;
+    println!("That took {:?}\n", Instant::now().duration_since(start));
+
+    start = Instant::now();
+    let router = Router::new(network, profile, blend);
+    let snapped: Vec<i64> = origins
+        .iter()
+        .filter_map(|&(x, y)| router.snap(x, y))
+        .collect();
+    println!(
+        "Snapped {} of {} origins to the network",
+        HumanCount(snapped.len() as u64),
+        HumanCount(origins.len() as u64)
+    );
+
+    let cost_per_edge = router.reachable_edges(snapped, limit);
+    println!(
+        "Found {} reachable edges within a cost of {limit}. That took {:?}",
+        HumanCount(cost_per_edge.len() as u64),
+        Instant::now().duration_since(start)
+    );
+
+    println!("Writing output GJ");
+    start = Instant::now();
+    network.write_geojson(output_path, cost_per_edge)?;
+    relabel_property(output_path, "count", "cost")?;
+    println!("That took {:?}", Instant::now().duration_since(start));
+
+    Ok(())
+}
+
+/// `Network::write_geojson` labels its per-edge number "count", which is right for OD counts but
+/// mislabels an isochrone's cost-to-reach. Rather than forking the writer, rewrite the property
+/// name on the file it just wrote.
+fn relabel_property(path: &str, from: &str, to: &str) -> Result<()> {
+    let mut gj: GeoJson = std::fs::read_to_string(path)?.parse()?;
+    if let GeoJson::FeatureCollection(collection) = &mut gj {
+        for feature in &mut collection.features {
+            if let Some(properties) = feature.properties.as_mut() {
+                if let Some(value) = properties.remove(from) {
+                    properties.insert(to.to_string(), value);
+                }
+            }
+        }
+    }
+    std::fs::write(path, gj.to_string())?;
+    Ok(())
+}
+
+/// Reads Point features out of a GeoJSON FeatureCollection.
+fn load_origins(path: &str) -> Result<Vec<(f64, f64)>> {
+    let gj = std::fs::read_to_string(path)?.parse::<GeoJson>()?;
+    let mut origins = Vec::new();
+    if let GeoJson::FeatureCollection(collection) = gj {
+        for feature in collection.features {
+            if let Some(geometry) = feature.geometry {
+                if let Value::Point(point) = geometry.value {
+                    origins.push((point[0], point[1]));
+                }
+            }
+        }
+    }
+    Ok(origins)
+}
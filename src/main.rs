@@ -1,14 +1,17 @@
+mod isochrone;
 mod osm2network;
+mod router;
 
 use std::collections::HashMap;
 use std::time::Instant;
 
-use anyhow::Result;
-use clap::Parser;
-use futures::{stream, StreamExt};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
 use geojson::{GeoJson, Value};
 use indicatif::{HumanCount, ProgressBar, ProgressStyle};
-use reqwest::Client;
+use rayon::prelude::*;
+
+use router::{Profile, Router};
 
 #[derive(Parser)]
 #[clap(about, version, author)]
@@ -18,16 +21,69 @@ struct Args {
     #[clap(long)]
     network: String,
 
-    /// A GeoJSON file with LineString requests
-    #[clap(long)]
-    requests: String,
+    /// Which LTS classifier to use when building the network from an osm.pbf file. Ignored when
+    /// loading a prebuilt .bin file, since it's already classified
+    #[clap(long, default_value = "green-mazovia")]
+    lts_profile: String,
 
-    /// How many requests to OSRM to have in-flight at once
-    #[clap(long, default_value_t = 10)]
-    concurrency: usize,
-    /// A percent (0 to 1000 -- note NOT 100) of requests to use
-    #[clap(long, default_value_t = 1000)]
-    sample_requests: usize,
+    #[clap(subcommand)]
+    mode: Mode,
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Route a set of OD LineString requests and count how many times each edge is used
+    Counts {
+        /// A GeoJSON file with LineString requests
+        #[clap(long)]
+        requests: String,
+
+        /// How many threads to route with in parallel
+        #[clap(long, default_value_t = 10)]
+        concurrency: usize,
+        /// A percent (0 to 1000 -- note NOT 100) of requests to use
+        #[clap(long, default_value_t = 1000)]
+        sample_requests: usize,
+
+        /// Which cost function to route with
+        #[clap(long, value_enum, default_value = "lts-weighted")]
+        profile: Profile,
+
+        /// Blend weight used by `--profile blend`: 0.0 behaves like `shortest`, 1.0 like
+        /// `lts-weighted`. Ignored by the other profiles
+        #[clap(long, default_value_t = 0.5)]
+        blend: f64,
+
+        /// Where to write the counts GeoJSON
+        #[clap(long, default_value = "output.geojson")]
+        output: String,
+    },
+    /// Compute everything reachable from a set of origin points within a cost budget, and emit
+    /// cost-per-edge as GeoJSON
+    Isochrone {
+        /// A GeoJSON file with origin Points
+        #[clap(long)]
+        origins: String,
+
+        /// The cost budget to expand from each origin. For `shortest`, this is meters; for
+        /// `lts-weighted` and `blend`, it's the same stress-scaled abstract cost used for
+        /// routing, not meters or minutes
+        #[clap(long)]
+        limit: usize,
+
+        /// Which cost function to expand with
+        #[clap(long, value_enum, default_value = "lts-weighted")]
+        profile: Profile,
+
+        /// Blend weight used by `--profile blend`: 0.0 behaves like `shortest`, 1.0 like
+        /// `lts-weighted`. Ignored by the other profiles
+        #[clap(long, default_value_t = 0.5)]
+        blend: f64,
+
+        /// Where to write the cost-per-edge GeoJSON
+        #[clap(long, default_value = "isochrone.geojson")]
+        output: String,
+    },
 }
 
 struct Counts {
@@ -35,82 +91,112 @@ struct Counts {
     errors: u64,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let args = Args::parse();
 
     let mut start = Instant::now();
     println!("Loading network from {}", args.network);
     let network = if args.network.ends_with(".osm.pbf") {
-        osm2network::Network::make_from_pbf(args.network)?
+        let lts_profile = lts::profile::resolve_profile(&args.lts_profile)
+            .ok_or_else(|| anyhow!("unknown --lts-profile {}", args.lts_profile))?;
+        osm2network::Network::make_from_pbf(args.network, lts_profile.as_ref())?
     } else {
         osm2network::Network::load_from_bin(args.network)?
     };
     println!("That took {:?}\n", Instant::now().duration_since(start));
 
-    start = Instant::now();
-    println!("Loading requests from {}", args.requests);
-    let requests = Request::load_from_geojson(&args.requests, args.sample_requests)?;
+    match args.mode {
+        Mode::Counts {
+            requests,
+            concurrency,
+            sample_requests,
+            profile,
+            blend,
+            output,
+        } => run_counts(
+            &network,
+            &requests,
+            concurrency,
+            sample_requests,
+            profile,
+            blend,
+            &output,
+        ),
+        Mode::Isochrone {
+            origins,
+            limit,
+            profile,
+            blend,
+            output,
+        } => isochrone::run(&network, &origins, limit, profile, blend, &output),
+    }
+}
+
+fn run_counts(
+    network: &osm2network::Network,
+    requests_path: &str,
+    concurrency: usize,
+    sample_requests: usize,
+    profile: Profile,
+    blend: f64,
+    output_path: &str,
+) -> Result<()> {
+    let mut start = Instant::now();
+    println!("Loading requests from {requests_path}");
+    let requests = Request::load_from_geojson(requests_path, sample_requests)?;
     println!("That took {:?}\n", Instant::now().duration_since(start));
 
     let num_requests = requests.len();
     println!(
-        "Making {} requests with concurrency = {}",
+        "Routing {} requests with {} threads",
         HumanCount(num_requests as u64),
-        args.concurrency
+        concurrency
     );
 
     start = Instant::now();
-    let results = stream::iter(requests)
-        .map(|req| tokio::spawn(async { req.calculate_route().await }))
-        .buffer_unordered(args.concurrency);
+    let router = Router::new(network, profile, blend);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()?;
 
     // Count routes per node pairs
     let progress = ProgressBar::new(num_requests as u64).with_style(ProgressStyle::with_template(
             "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({per_sec}, {eta})").unwrap());
+    let results: Vec<Result<Vec<i64>>> = pool.install(|| {
+        requests
+            .into_par_iter()
+            .map(|req| {
+                let result = req.calculate_route(&router);
+                progress.inc(1);
+                result
+            })
+            .collect()
+    });
+    progress.finish();
+
     let mut counts = Counts {
         count_per_edge: HashMap::new(),
         errors: 0,
     };
-    results
-        .fold(&mut counts, |accumulate, future| async {
-            progress.inc(1);
-            // TODO Flatten
-            match future {
-                Ok(result) => match result {
-                    Ok(nodes) => {
-                        // OSRM returns all nodes, but we only consider some to be intersections
-                        // TODO When the route begins or ends with an intermediate non-intersection
-                        // node, we don't handle it well yet
-                        let mut i1 = nodes[0];
-                        let mut last = nodes[0];
-                        for node in nodes.into_iter().skip(1) {
-                            if network.intersections.contains(&node) {
-                                *accumulate.count_per_edge.entry((i1, node)).or_insert(0) += 1;
-                                i1 = node;
-                            }
-                            last = node;
-                        }
-                        if i1 != last && false {
-                            println!("We didn't end on an intersection... {i1} to {last}");
-                        }
+    for result in results {
+        match result {
+            Ok(nodes) => {
+                // Both endpoints are snapped to intersections and the router only returns
+                // intersection nodes, so each consecutive pair is an edge
+                let mut i1 = nodes[0];
+                for node in nodes.into_iter().skip(1) {
+                    if network.intersections.contains(&node) {
+                        *counts.count_per_edge.entry((i1, node)).or_insert(0) += 1;
+                        i1 = node;
                     }
-                    Err(err) => {
-                        // TODO Usually the API being overloaded
-                        if false {
-                            println!("Request failed: {err}");
-                        }
-                        accumulate.errors += 1;
-                    }
-                },
-                Err(err) => {
-                    println!("Tokio error: {err}");
                 }
             }
-            accumulate
-        })
-        .await;
-    progress.finish();
+            Err(_) => {
+                // Usually means no route connects the two snapped endpoints
+                counts.errors += 1;
+            }
+        }
+    }
 
     println!(
         "Got counts for {} edges. That took {:?}",
@@ -121,7 +207,7 @@ async fn main() -> Result<()> {
 
     println!("Writing output GJ");
     start = Instant::now();
-    network.write_geojson("output.geojson", counts.count_per_edge)?;
+    network.write_geojson(output_path, counts.count_per_edge)?;
     println!("That took {:?}", Instant::now().duration_since(start));
 
     Ok(())
@@ -136,31 +222,16 @@ struct Request {
 
 impl Request {
     // Returns OSM node IDs
-    async fn calculate_route(self) -> Result<Vec<i64>> {
-        // TODO How to share, and does it matter?
-        let client = Client::new();
-
-        // Alternatively, try bindings (https://crates.io/crates/rsc_osrm)
-        let body = client
-            .get(format!(
-                "http://localhost:5000/route/v1/driving/{},{};{},{}",
-                self.x1, self.y1, self.x2, self.y2
-            ))
-            .query(&[
-                ("overview", "false"),
-                ("alternatives", "false"),
-                ("steps", "false"),
-                ("annotations", "nodes"),
-            ])
-            .send()
-            .await?
-            .text()
-            .await?;
-        let json_value: serde_json::Value = serde_json::from_str(&body)?;
-        let nodes: Vec<i64> = serde_json::from_value(
-            json_value["routes"][0]["legs"][0]["annotation"]["nodes"].clone(),
-        )?;
-        Ok(nodes)
+    fn calculate_route(self, router: &Router) -> Result<Vec<i64>> {
+        let start = router
+            .snap(self.x1, self.y1)
+            .ok_or_else(|| anyhow!("no intersection near ({}, {})", self.x1, self.y1))?;
+        let end = router
+            .snap(self.x2, self.y2)
+            .ok_or_else(|| anyhow!("no intersection near ({}, {})", self.x2, self.y2))?;
+        router
+            .route(start, end)
+            .ok_or_else(|| anyhow!("no route found between {start} and {end}"))
     }
 
     fn load_from_geojson(path: &str, sample_requests: usize) -> Result<Vec<Request>> {
@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use clap::ValueEnum;
+use lts::LTS;
+use rstar::{RTree, RTreeObject, AABB};
+
+use crate::osm2network::{Edge, Network};
+
+/// Which cost function to route with.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Profile {
+    /// Minimize distance, ignoring traffic stress entirely
+    Shortest,
+    /// Minimize distance scaled by an LTS stress penalty, so routes detour around high-stress
+    /// roads the way a stress-averse cyclist would
+    LtsWeighted,
+    /// A tunable blend of `shortest` and `lts-weighted`, controlled by `--blend`
+    Blend,
+}
+
+impl Profile {
+    /// Returns the cost of traversing `edge`, or `None` if it's not passable at all
+    /// (`LTS::NotAllowed`, in every profile; LTS 4 edges stay passable, just costly). `blend` only
+    /// matters for `Profile::Blend`: 0.0 behaves like `shortest`, 1.0 like `lts-weighted`.
+    fn edge_cost(self, edge: &Edge, blend: f64) -> Option<usize> {
+        let length = edge.length_meters;
+        match self {
+            Profile::Shortest => {
+                if edge.lts == LTS::NotAllowed {
+                    return None;
+                }
+                Some(length.round() as usize)
+            }
+            Profile::LtsWeighted => {
+                let penalty = stress_penalty(edge.lts)?;
+                Some((length * penalty).round() as usize)
+            }
+            Profile::Blend => {
+                let penalty = stress_penalty(edge.lts)?;
+                let weighted = length * penalty;
+                Some((length * (1.0 - blend) + weighted * blend).round() as usize)
+            }
+        }
+    }
+}
+
+/// How much more costly an edge is to a stress-averse cyclist, relative to its raw length.
+/// `LTS::NotAllowed` has no penalty because such edges are excluded from the graph entirely.
+fn stress_penalty(lts: LTS) -> Option<f64> {
+    match lts {
+        LTS::LTS1 => Some(1.0),
+        LTS::LTS2 => Some(1.5),
+        LTS::LTS3 => Some(4.0),
+        LTS::LTS4 => Some(20.0),
+        LTS::NotAllowed => None,
+    }
+}
+
+/// An in-process router over `osm2network::Network`, used instead of shelling out to a
+/// locally-running OSRM server. Builds an adjacency list keyed by intersection node IDs and
+/// answers shortest-path queries with Dijkstra.
+pub struct Router {
+    adjacency: HashMap<i64, Vec<(i64, usize)>>,
+    nearest_intersection: RTree<SnappedNode>,
+}
+
+struct SnappedNode {
+    node: i64,
+    point: [f64; 2],
+}
+
+impl RTreeObject for SnappedNode {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point(self.point)
+    }
+}
+
+impl Router {
+    /// `blend` is the weight used by `Profile::Blend` (0.0 = shortest, 1.0 = lts-weighted);
+    /// it's ignored by the other profiles.
+    pub fn new(network: &Network, profile: Profile, blend: f64) -> Self {
+        let mut adjacency: HashMap<i64, Vec<(i64, usize)>> = HashMap::new();
+        for ((i1, i2), edge) in &network.edges {
+            let Some(cost) = profile.edge_cost(edge, blend) else {
+                continue;
+            };
+            adjacency.entry(*i1).or_default().push((*i2, cost));
+            adjacency.entry(*i2).or_default().push((*i1, cost));
+        }
+
+        let snap_points = network
+            .intersections
+            .iter()
+            .filter_map(|node| {
+                network
+                    .node_points
+                    .get(node)
+                    .map(|pt| SnappedNode { node: *node, point: [pt.0, pt.1] })
+            })
+            .collect();
+
+        Self {
+            adjacency,
+            nearest_intersection: RTree::bulk_load(snap_points),
+        }
+    }
+
+    /// Snaps an arbitrary (x, y) to the nearest intersection node.
+    pub fn snap(&self, x: f64, y: f64) -> Option<i64> {
+        self.nearest_intersection
+            .nearest_neighbor(&[x, y])
+            .map(|snapped| snapped.node)
+    }
+
+    /// Runs Dijkstra from `start` to `end`, returning the sequence of intersection node IDs
+    /// making up the cheapest path, or `None` if `end` is unreachable.
+    pub fn route(&self, start: i64, end: i64) -> Option<Vec<i64>> {
+        let mut best_cost: HashMap<i64, usize> = HashMap::new();
+        let mut predecessor: HashMap<i64, i64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        best_cost.insert(start, 0);
+        queue.push(PriorityQueueItem { cost: 0, node: start });
+
+        while let Some(PriorityQueueItem { cost, node }) = queue.pop() {
+            if node == end {
+                break;
+            }
+            if cost > *best_cost.get(&node).unwrap_or(&usize::MAX) {
+                continue;
+            }
+            let Some(neighbors) = self.adjacency.get(&node) else {
+                continue;
+            };
+            for &(next, edge_cost) in neighbors {
+                let next_cost = cost + edge_cost;
+                if next_cost < *best_cost.get(&next).unwrap_or(&usize::MAX) {
+                    best_cost.insert(next, next_cost);
+                    predecessor.insert(next, node);
+                    queue.push(PriorityQueueItem { cost: next_cost, node: next });
+                }
+            }
+        }
+
+        if !best_cost.contains_key(&end) {
+            return None;
+        }
+
+        let mut path = vec![end];
+        while *path.last().unwrap() != start {
+            path.push(*predecessor.get(path.last().unwrap())?);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Runs a multi-source Dijkstra from `starts`, expanding until the accumulated cost would
+    /// exceed `limit`. Returns the minimum cost to reach each traversed directed edge.
+    pub fn reachable_edges(
+        &self,
+        starts: impl IntoIterator<Item = i64>,
+        limit: usize,
+    ) -> HashMap<(i64, i64), usize> {
+        let mut best_cost: HashMap<i64, usize> = HashMap::new();
+        let mut edge_cost: HashMap<(i64, i64), usize> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        for start in starts {
+            if *best_cost.get(&start).unwrap_or(&usize::MAX) > 0 {
+                best_cost.insert(start, 0);
+                queue.push(PriorityQueueItem { cost: 0, node: start });
+            }
+        }
+
+        while let Some(PriorityQueueItem { cost, node }) = queue.pop() {
+            if cost > *best_cost.get(&node).unwrap_or(&usize::MAX) {
+                continue;
+            }
+            let Some(neighbors) = self.adjacency.get(&node) else {
+                continue;
+            };
+            for &(next, edge_weight) in neighbors {
+                let next_cost = cost + edge_weight;
+                if next_cost > limit {
+                    continue;
+                }
+                edge_cost
+                    .entry((node, next))
+                    .and_modify(|existing| *existing = (*existing).min(next_cost))
+                    .or_insert(next_cost);
+
+                if next_cost < *best_cost.get(&next).unwrap_or(&usize::MAX) {
+                    best_cost.insert(next, next_cost);
+                    queue.push(PriorityQueueItem { cost: next_cost, node: next });
+                }
+            }
+        }
+
+        edge_cost
+    }
+}
+
+/// An entry in the Dijkstra frontier. `BinaryHeap` is a max-heap, so `Ord` is reversed on cost to
+/// make it behave like a min-heap.
+#[derive(PartialEq, Eq)]
+struct PriorityQueueItem {
+    cost: usize,
+    node: i64,
+}
+
+impl Ord for PriorityQueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for PriorityQueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}